@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use crate::values::*;
+use crate::parser::Node;
+
+/// A substitution maps type-variable ids to the types they have been bound to.
+/// It is threaded through unification and applied lazily whenever a concrete
+/// type is needed.
+#[derive(Clone, Default)]
+pub struct Subst(HashMap<usize, Type>);
+impl Subst {
+    pub fn new() -> Self { Self(HashMap::new()) }
+    /// Resolve a type through the current substitution, following chains of
+    /// bound variables until a non-variable (or a free variable) is reached and
+    /// recursing structurally into the remaining type.
+    pub fn apply(&self, typ: &Type) -> Type {
+        match typ {
+            Type::Var(id) => match self.0.get(id) {
+                Some(bound) => self.apply(bound),
+                None => Type::Var(*id)
+            }
+            Type::Function => Type::Function,
+            Type::List(inner) => Type::List(Box::new(self.apply(inner))),
+            Type::Map(inner) => Type::Map(Box::new(self.apply(inner))),
+            Type::Fn(args, ret) => Type::Fn(args.iter().map(|t| self.apply(t)).collect(), Box::new(self.apply(ret))),
+            Type::Union(types) => Type::Union(types.iter().map(|t| self.apply(t)).collect()),
+            Type::Forall(vars, inner) => Type::Forall(vars.clone(), Box::new(self.apply(inner))),
+            other => other.clone()
+        }
+    }
+    fn bind(&mut self, id: usize, typ: Type) {
+        self.0.insert(id, typ);
+    }
+    /// Structural equality that resolves both sides through the substitution
+    /// first, so a variable bound to `Int` compares equal to `Int`.
+    pub fn equal(&self, a: &Type, b: &Type) -> bool {
+        self.apply(a) == self.apply(b)
+    }
+    /// Cast `value` to `typ` after resolving `typ` through the substitution, so
+    /// a bound type variable casts as its concrete binding rather than as a var.
+    pub fn cast(&self, typ: &Type, value: &V) -> V {
+        self.apply(typ).cast(value)
+    }
+}
+
+/// Supplies fresh, never-before-used type variables for instantiation.
+#[derive(Default)]
+pub struct Supply(usize);
+impl Supply {
+    pub fn new() -> Self { Self(0) }
+    pub fn fresh(&mut self) -> Type {
+        let id = self.0;
+        self.0 += 1;
+        Type::Var(id)
+    }
+    /// Advance the counter so the next `fresh` id is strictly greater than every
+    /// variable already appearing in `typ`. Used before instantiation so the
+    /// fresh variables cannot collide with (and capture) the scheme's own ids.
+    fn advance_past(&mut self, typ: &Type) {
+        if let Some(max) = max_var(typ) {
+            if self.0 <= max { self.0 = max + 1 }
+        }
+    }
+}
+
+/// The largest type-variable id occurring anywhere in `typ`, including ids bound
+/// by a `Forall`, or `None` if the type mentions no variables.
+fn max_var(typ: &Type) -> Option<usize> {
+    match typ {
+        Type::Var(id) => Some(*id),
+        Type::List(inner) | Type::Map(inner) => max_var(inner),
+        Type::Fn(args, ret) => args.iter().filter_map(max_var).chain(max_var(ret)).max(),
+        Type::Union(types) => types.iter().filter_map(max_var).max(),
+        Type::Forall(vars, inner) => vars.iter().copied().chain(max_var(inner)).max(),
+        _ => None
+    }
+}
+
+/// A typing environment mapping bound names to their (possibly quantified)
+/// type schemes.
+#[derive(Clone, Default)]
+pub struct Env(HashMap<String, Type>);
+impl Env {
+    pub fn new() -> Self { Self(HashMap::new()) }
+    pub fn get(&self, name: &str) -> Option<&Type> { self.0.get(name) }
+    pub fn insert(&mut self, name: String, scheme: Type) { self.0.insert(name, scheme); }
+    fn free_vars(&self) -> Vec<usize> {
+        let mut vars = vec![];
+        for scheme in self.0.values() {
+            free_vars(scheme, &mut vars);
+        }
+        vars
+    }
+}
+
+/// Collect the free (unbound) type variables of a type, skipping any that are
+/// quantified by an enclosing `Forall`.
+fn free_vars(typ: &Type, out: &mut Vec<usize>) {
+    match typ {
+        Type::Var(id) => if !out.contains(id) { out.push(*id) },
+        Type::List(inner) | Type::Map(inner) => free_vars(inner, out),
+        Type::Fn(args, ret) => {
+            for t in args { free_vars(t, out) }
+            free_vars(ret, out);
+        }
+        Type::Union(types) => for t in types { free_vars(t, out) },
+        Type::Forall(bound, inner) => {
+            let mut inner_vars = vec![];
+            free_vars(inner, &mut inner_vars);
+            for id in inner_vars {
+                if !bound.contains(&id) && !out.contains(&id) { out.push(id) }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Does `typ` mention the variable `id`? Used as the occurs-check that keeps
+/// unification from building an infinite type.
+fn occurs(id: usize, typ: &Type, subst: &Subst) -> bool {
+    match subst.apply(typ) {
+        Type::Var(other) => other == id,
+        Type::List(inner) | Type::Map(inner) => occurs(id, &inner, subst),
+        Type::Fn(args, ret) => args.iter().any(|t| occurs(id, t, subst)) || occurs(id, &ret, subst),
+        Type::Union(types) => types.iter().any(|t| occurs(id, t, subst)),
+        Type::Forall(_, inner) => occurs(id, &inner, subst),
+        _ => false
+    }
+}
+
+/// Unify two types under the running substitution, binding free variables so
+/// that `a` and `b` become equal. Returns an error message on a mismatch or a
+/// failed occurs-check.
+pub fn unify(a: &Type, b: &Type, subst: &mut Subst) -> Result<(), String> {
+    let a = subst.apply(a);
+    let b = subst.apply(b);
+    match (&a, &b) {
+        (Type::Var(id), other) | (other, Type::Var(id)) => {
+            if let Type::Var(other_id) = other {
+                if other_id == id { return Ok(()) }
+            }
+            if occurs(*id, other, subst) {
+                return Err(format!("recursive type: t{id} occurs in {other}"));
+            }
+            subst.bind(*id, other.clone());
+            Ok(())
+        }
+        (Type::List(x), Type::List(y)) | (Type::Map(x), Type::Map(y)) => unify(x, y, subst),
+        (Type::Fn(xa, xr), Type::Fn(ya, yr)) if xa.len() == ya.len() => {
+            for (x, y) in xa.iter().zip(ya) { unify(x, y, subst)? }
+            unify(xr, yr, subst)
+        }
+        (Type::Union(xs), Type::Union(ys)) if xs.len() == ys.len() => {
+            for (x, y) in xs.iter().zip(ys) { unify(x, y, subst)? }
+            Ok(())
+        }
+        _ => if a == b { Ok(()) } else { Err(format!("cannot unify {a} with {b}")) }
+    }
+}
+
+/// Close `typ` over the variables free in it but not in `env`, producing a
+/// `Forall` scheme. Variables already constrained by the environment stay free.
+pub fn generalize(env: &Env, typ: &Type, subst: &Subst) -> Type {
+    let typ = subst.apply(typ);
+    let env_vars = env.free_vars();
+    let mut vars = vec![];
+    free_vars(&typ, &mut vars);
+    let quantified: Vec<usize> = vars.into_iter().filter(|id| !env_vars.contains(id)).collect();
+    if quantified.is_empty() { typ } else { Type::Forall(quantified, Box::new(typ)) }
+}
+
+/// Instantiate a scheme by replacing each quantified variable with a fresh one,
+/// yielding a type usable at a single occurrence. Non-schemes pass through.
+pub fn instantiate(scheme: &Type, supply: &mut Supply) -> Type {
+    match scheme {
+        Type::Forall(vars, inner) => {
+            supply.advance_past(scheme);
+            let mut mapping = HashMap::new();
+            for id in vars { mapping.insert(*id, supply.fresh()); }
+            rename(inner, &mapping)
+        }
+        other => other.clone()
+    }
+}
+
+fn rename(typ: &Type, mapping: &HashMap<usize, Type>) -> Type {
+    match typ {
+        Type::Var(id) => mapping.get(id).cloned().unwrap_or(Type::Var(*id)),
+        Type::List(inner) => Type::List(Box::new(rename(inner, mapping))),
+        Type::Map(inner) => Type::Map(Box::new(rename(inner, mapping))),
+        Type::Fn(args, ret) => Type::Fn(args.iter().map(|t| rename(t, mapping)).collect(), Box::new(rename(ret, mapping))),
+        Type::Union(types) => Type::Union(types.iter().map(|t| rename(t, mapping)).collect()),
+        Type::Forall(vars, inner) => Type::Forall(vars.clone(), Box::new(rename(inner, mapping))),
+        other => other.clone()
+    }
+}
+
+/// Infer the principal type of a parser [`Node`] under `env`, extending `subst`
+/// with the bindings unification discovers. Literals map to their concrete
+/// type, a name is looked up and instantiated, a body types to its last
+/// expression, a lambda introduces fresh variables for its parameters and
+/// builds a `Type::Fn`, and an application unifies the callee against a fresh
+/// `Type::Fn`. Unknown forms yield a fresh variable so inference stays total.
+pub fn infer(node: &Node, env: &Env, subst: &mut Subst, supply: &mut Supply) -> Result<Type, String> {
+    match node {
+        Node::Null => Ok(Type::Undefined),
+        Node::Int(_) => Ok(Type::Int),
+        Node::Float(_) => Ok(Type::Float),
+        Node::Bool(_) => Ok(Type::Bool),
+        Node::String(_) => Ok(Type::String),
+        Node::Var(name) => match env.get(name) {
+            Some(scheme) => Ok(instantiate(scheme, supply)),
+            None => Err(format!("unbound variable {name}"))
+        }
+        Node::Body(nodes) => {
+            let mut last = Type::Undefined;
+            for n in nodes { last = infer(n, env, subst, supply)? }
+            Ok(last)
+        }
+        Node::Closure(params, body) | Node::Function(params, body) => Ok(instantiate(&infer_fn(params, body, env, subst, supply)?, supply)),
+        Node::Call(func, args) => {
+            let func_type = infer(func, env, subst, supply)?;
+            let arg_types = args.iter().map(|a| infer(a, env, subst, supply)).collect::<Result<Vec<_>, _>>()?;
+            let ret = supply.fresh();
+            unify(&func_type, &Type::Fn(arg_types, Box::new(ret.clone())), subst)?;
+            Ok(subst.apply(&ret))
+        }
+        _ => Ok(supply.fresh())
+    }
+}
+
+/// Infer the closed scheme of a function given its parameter nodes and body:
+/// each parameter gets a fresh variable bound in a child environment, the body
+/// is inferred against it, and the resulting `Type::Fn` is generalized over the
+/// variables that stayed free.
+fn infer_fn(params: &[Node], body: &Node, env: &Env, subst: &mut Subst, supply: &mut Supply) -> Result<Type, String> {
+    let mut inner = env.clone();
+    let mut arg_types = vec![];
+    for param in params {
+        let var = supply.fresh();
+        if let Some(name) = node_name(param) { inner.insert(name, var.clone()) }
+        arg_types.push(var);
+    }
+    let ret = infer(body, &inner, subst, supply)?;
+    let args = arg_types.iter().map(|t| subst.apply(t)).collect();
+    let fn_type = Type::Fn(args, Box::new(subst.apply(&ret)));
+    Ok(generalize(env, &fn_type, subst))
+}
+
+/// The bound name of a parameter node, if it is a plain identifier.
+fn node_name(node: &Node) -> Option<String> {
+    match node {
+        Node::Var(name) => Some(name.clone()),
+        _ => None
+    }
+}
+
+/// Run inference over a runtime value: `Function`/`Closure` values report the
+/// generalized principal type of their body, every other value reports its
+/// concrete [`Type`]. This is the bridge that lets [`V::infer`] attach an
+/// inferred scheme to the function and closure values the evaluator builds.
+pub fn infer_value(value: &V) -> Result<Type, String> {
+    let mut subst = Subst::new();
+    let mut supply = Supply::new();
+    let env = Env::new();
+    match value {
+        V::Function(params, body) => infer_fn(params, body, &env, &mut subst, &mut supply),
+        V::Closure(body, _) => infer_fn(&[], body, &env, &mut subst, &mut supply),
+        other => Ok(other.typ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unify_binds_var_to_concrete() {
+        let mut subst = Subst::new();
+        unify(&Type::Var(0), &Type::Int, &mut subst).unwrap();
+        assert_eq!(subst.apply(&Type::Var(0)), Type::Int);
+        assert!(subst.equal(&Type::Var(0), &Type::Int));
+    }
+
+    #[test]
+    fn unify_recurses_through_fn() {
+        let mut subst = Subst::new();
+        let a = Type::Fn(vec![Type::Var(0)], Box::new(Type::Var(0)));
+        let b = Type::Fn(vec![Type::Int], Box::new(Type::Var(1)));
+        unify(&a, &b, &mut subst).unwrap();
+        assert_eq!(subst.apply(&Type::Var(1)), Type::Int);
+    }
+
+    #[test]
+    fn occurs_check_rejects_infinite_type() {
+        let mut subst = Subst::new();
+        let recursive = Type::List(Box::new(Type::Var(0)));
+        assert!(occurs(0, &recursive, &subst));
+        assert!(unify(&Type::Var(0), &recursive, &mut subst).is_err());
+    }
+
+    #[test]
+    fn generalize_closes_over_free_vars() {
+        let subst = Subst::new();
+        let env = Env::new();
+        let identity = Type::Fn(vec![Type::Var(0)], Box::new(Type::Var(0)));
+        match generalize(&env, &identity, &subst) {
+            Type::Forall(vars, _) => assert_eq!(vars, vec![0]),
+            other => panic!("expected a scheme, got {other}")
+        }
+    }
+
+    #[test]
+    fn generalize_keeps_env_bound_vars_free() {
+        let subst = Subst::new();
+        let mut env = Env::new();
+        env.insert("x".to_string(), Type::Var(0));
+        let typ = Type::Fn(vec![Type::Var(0)], Box::new(Type::Var(1)));
+        match generalize(&env, &typ, &subst) {
+            Type::Forall(vars, _) => assert_eq!(vars, vec![1]),
+            other => panic!("expected a scheme, got {other}")
+        }
+    }
+
+    #[test]
+    fn instantiate_renames_quantified_vars() {
+        let mut supply = Supply::new();
+        let scheme = Type::Forall(vec![0], Box::new(Type::Fn(vec![Type::Var(0)], Box::new(Type::Var(0)))));
+        match instantiate(&scheme, &mut supply) {
+            Type::Fn(args, ret) => {
+                assert_ne!(args[0], Type::Var(0));
+                assert_eq!(args[0], *ret);
+            }
+            other => panic!("expected a function type, got {other}")
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use std::sync::{Arc, RwLock};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::*;
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use serde::ser::Error as _;
 use crate::error::*;
 use crate::context::*;
 use crate::parser::*;
@@ -6,10 +11,14 @@ use crate::position::Position;
 
 pub type NativFunction = fn(Vec<V>, &mut Context, &Position, &Vec<&Position>) -> Result<(V, R), E>;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Type {
-    Undefined, Any, Int, Float, Bool, String, NativFunction, Function,
+    Undefined, Any, Int, Float, Decimal, Bool, String, NativFunction, Function,
     Addr, Closure,
+    List(Box<Type>), Map(Box<Type>),
+    Reference(Box<Type>),
+    Fn(Vec<Type>, Box<Type>),
+    Var(usize), Forall(Vec<usize>, Box<Type>),
     Union(Vec<Type>), Type
 }
 impl Type {
@@ -21,6 +30,7 @@ impl Type {
                 V::Null => V::Int(0),
                 V::Int(v) => V::Int(*v),
                 V::Float(v) => V::Int(*v as i64),
+                V::Decimal(v) => V::Int(v.to_i64().unwrap_or(0)),
                 V::Bool(v) => V::Int(*v as i64),
                 _ => V::Null
             }
@@ -28,19 +38,42 @@ impl Type {
                 V::Null => V::Float(0.0),
                 V::Int(v) => V::Float(*v as f64),
                 V::Float(v) => V::Float(*v),
+                V::Decimal(v) => V::Float(v.to_f64().unwrap_or(0.0)),
                 V::Bool(v) => V::Float((*v as i64) as f64),
                 _ => V::Null
             }
+            Self::Decimal => match value {
+                V::Null => V::Decimal(Decimal::ZERO),
+                V::Int(v) => V::Decimal(Decimal::from(*v)),
+                V::Float(v) => V::Decimal(Decimal::from_f64(*v).unwrap_or(Decimal::ZERO)),
+                V::Decimal(v) => V::Decimal(*v),
+                V::Bool(v) => V::Decimal(Decimal::from(*v as i64)),
+                _ => V::Null
+            }
             Self::Bool => match value {
                 V::Null => V::Bool(false),
                 V::Int(v) => V::Bool(*v != 0),
                 V::Float(v) => V::Bool(*v != 0.0),
+                V::Decimal(v) => V::Bool(!v.is_zero()),
                 V::Bool(v) => V::Bool(*v),
                 _ => V::Null
             }
             Self::String => V::String(value.to_string()),
             Self::Addr => V::Addr(value.to_string()),
+            Self::List(typ) => match value {
+                V::List(values) => V::List(values.iter().map(|v| typ.cast(v)).collect()),
+                _ => V::Null
+            }
+            Self::Map(typ) => match value {
+                V::Map(entries) => V::Map(entries.iter().map(|(k, v)| (k.clone(), typ.cast(v))).collect()),
+                _ => V::Null
+            }
+            Self::Reference(typ) => V::Reference(Arc::new(typ.cast(&value.deref()))),
             Self::Type => V::Type(value.typ()),
+            Self::Fn(_, _) => match value {
+                V::Function(_, _) | V::Closure(_, _) | V::NativFunction(_, _) => value.clone(),
+                _ => V::Null
+            }
             _ => V::Null
         }
     }
@@ -57,12 +90,19 @@ impl std::fmt::Debug for Type {
             Self::Any => write!(f, "any"),
             Self::Int => write!(f, "int"),
             Self::Float => write!(f, "float"),
+            Self::Decimal => write!(f, "decimal"),
             Self::Bool => write!(f, "bool"),
             Self::String => write!(f, "str"),
             Self::NativFunction => write!(f, "nativ-function"),
             Self::Function => write!(f, "function"),
             Self::Addr => write!(f, "addr"),
             Self::Closure => write!(f, "closure"),
+            Self::List(typ) => write!(f, "[{typ}]"),
+            Self::Map(typ) => write!(f, "{{{typ}}}"),
+            Self::Reference(typ) => write!(f, "&{typ}"),
+            Self::Fn(args, ret) => write!(f, "fn({}) -> {ret}", args.iter().map(|a| a.to_string()).collect::<Vec<String>>().join(", ")),
+            Self::Var(id) => write!(f, "t{id}"),
+            Self::Forall(vars, typ) => write!(f, "forall {}. {typ}", vars.iter().map(|v| format!("t{v}")).collect::<Vec<String>>().join(" ")),
             Self::Union(types) => write!(f, "{}", types.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("|")),
             Self::Type => write!(f, "type"),
         }
@@ -94,12 +134,19 @@ impl PartialEq for Type {
             (Self::Undefined, Self::Undefined) => true,
             (Self::Int, Self::Int) => true,
             (Self::Float, Self::Float) => true,
+            (Self::Decimal, Self::Decimal) => true,
             (Self::Bool, Self::Bool) => true,
             (Self::String, Self::String) => true,
             (Self::Addr, Self::Addr) => true,
             (Self::Closure, Self::Closure) => true,
             (Self::NativFunction, Self::NativFunction) => true,
             (Self::Function, Self::Function) => true,
+            (Self::List(t1), Self::List(t2)) => t1 == t2,
+            (Self::Map(t1), Self::Map(t2)) => t1 == t2,
+            (Self::Reference(t1), Self::Reference(t2)) => t1 == t2,
+            (Self::Fn(a1, r1), Self::Fn(a2, r2)) => a1 == a2 && r1 == r2,
+            (Self::Var(a), Self::Var(b)) => a == b,
+            (Self::Forall(v1, t1), Self::Forall(v2, t2)) => v1 == v2 && t1 == t2,
             (Self::Type, Self::Type) => true,
             _ => false
         }
@@ -108,8 +155,10 @@ impl PartialEq for Type {
 
 #[derive(Clone)]
 pub enum V {
-    Null, Wirldcard, Int(i64), Float(f64), Bool(bool), String(String),
-    Addr(String), Closure(Node),
+    Null, Wirldcard, Int(i64), Float(f64), Decimal(Decimal), Bool(bool), String(String),
+    Addr(String), Closure(Node, Arc<RwLock<Context>>),
+    List(Vec<V>), Map(Vec<(String, V)>),
+    Reference(Arc<V>), Mutable(Arc<RwLock<V>>),
     NativFunction(Vec<Type>, NativFunction), Function(Vec<Node>, Node),
     Type(Type)
 }
@@ -120,79 +169,285 @@ impl V {
             Self::Wirldcard => Type::Any,
             Self::Int(_) => Type::Int,
             Self::Float(_) => Type::Float,
+            Self::Decimal(_) => Type::Decimal,
             Self::Bool(_) => Type::Bool,
             Self::String(_) => Type::String,
             Self::Addr(_) => Type::Addr,
-            Self::Closure(_) => Type::Closure,
+            Self::Closure(_, _) => Type::Closure,
+            Self::Reference(inner) => inner.typ(),
+            Self::Mutable(inner) => inner.read().unwrap().typ(),
+            Self::List(values) => Type::List(Box::new(match values.first() {
+                Some(v) => v.typ(),
+                None => Type::Any
+            })),
+            Self::Map(entries) => Type::Map(Box::new(match entries.first() {
+                Some((_, v)) => v.typ(),
+                None => Type::Any
+            })),
             Self::NativFunction(_, _) => Type::NativFunction,
             Self::Function(_, _) => Type::Function,
             Self::Type(_) => Type::Type,
         }
     }
+    /// Build a closure that captures its defining scope. The evaluator calls
+    /// this at closure-construction time, sharing the live [`Context`] by
+    /// cloning the `Arc` rather than copying it, so the closure keeps reading
+    /// and writing the same bindings after the defining scope has exited.
+    pub fn closure(body: Node, defining: &Arc<RwLock<Context>>) -> V {
+        V::Closure(body, Arc::clone(defining))
+    }
+    /// Resolve through any `Reference`/`Mutable` layers to the underlying value,
+    /// cloning it out. Scalars and aggregates are returned unchanged so callers
+    /// can treat a shared `Mutable` integer exactly like a plain integer.
+    pub fn deref(&self) -> V {
+        match self {
+            Self::Reference(inner) => inner.deref(),
+            Self::Mutable(inner) => inner.read().unwrap().deref(),
+            other => other.clone()
+        }
+    }
     pub fn add(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().add(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.add(&other.deref()) }
         match self {
             Self::Int(v1) => match other {
                 Self::Int(v2) => Some(V::Int(v1 + v2)),
                 Self::Float(v2) => Some(V::Float((*v1 as f64) + v2)),
+                Self::Decimal(v2) => Some(V::Decimal(Decimal::from(*v1) + v2)),
                 _ => None
             }
             Self::Float(v1) => match other {
                 Self::Float(v2) => Some(V::Float(v1 + v2)),
                 Self::Int(v2) => Some(V::Float(v1 + (*v2 as f64))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal(*v1) + v2)),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Decimal(v2) => Some(V::Decimal(v1 + v2)),
+                Self::Int(v2) => Some(V::Decimal(v1 + Decimal::from(*v2))),
+                Self::Float(v2) => Some(V::Decimal(v1 + Self::float_to_decimal(*v2))),
                 _ => None
             }
             Self::String(v1) => match other {
                 Self::String(v2) => Some(V::String(v1.to_owned() + v2)),
                 _ => None
             }
+            Self::List(v1) => match other {
+                Self::List(v2) => {
+                    let mut values = v1.clone();
+                    values.extend(v2.clone());
+                    Some(V::List(values))
+                }
+                _ => None
+            }
+            Self::Map(v1) => match other {
+                Self::Map(v2) => {
+                    let mut entries = v1.clone();
+                    for (key, value) in v2 {
+                        match entries.iter_mut().find(|(k, _)| k == key) {
+                            Some(entry) => entry.1 = value.clone(),
+                            None => entries.push((key.clone(), value.clone()))
+                        }
+                    }
+                    Some(V::Map(entries))
+                }
+                _ => None
+            }
+            _ => None
+        }
+    }
+    pub fn index(&self, index: &V) -> Option<V> {
+        match self {
+            Self::List(values) => match index {
+                Self::Int(i) => values.get(*i as usize).cloned(),
+                _ => None
+            }
+            Self::Map(entries) => match index {
+                Self::String(key) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone()),
+                _ => None
+            }
+            _ => None
+        }
+    }
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Self::List(values) => Some(values.len()),
+            Self::Map(entries) => Some(entries.len()),
+            Self::String(s) => Some(s.chars().count()),
             _ => None
         }
     }
     pub fn sub(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().sub(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.sub(&other.deref()) }
         match self {
             Self::Int(v1) => match other {
                 Self::Int(v2) => Some(V::Int(v1 - v2)),
                 Self::Float(v2) => Some(V::Float((*v1 as f64) - v2)),
+                Self::Decimal(v2) => Some(V::Decimal(Decimal::from(*v1) - v2)),
                 _ => None
             }
             Self::Float(v1) => match other {
                 Self::Float(v2) => Some(V::Float(v1 - v2)),
                 Self::Int(v2) => Some(V::Float(v1 - (*v2 as f64))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal(*v1) - v2)),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Decimal(v2) => Some(V::Decimal(v1 - v2)),
+                Self::Int(v2) => Some(V::Decimal(v1 - Decimal::from(*v2))),
+                Self::Float(v2) => Some(V::Decimal(v1 - Self::float_to_decimal(*v2))),
                 _ => None
             }
             _ => None
         }
     }
     pub fn mul(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().mul(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.mul(&other.deref()) }
         match self {
             Self::Int(v1) => match other {
                 Self::Int(v2) => Some(V::Int(v1 * v2)),
                 Self::Float(v2) => Some(V::Float((*v1 as f64) * v2)),
+                Self::Decimal(v2) => Some(V::Decimal(Decimal::from(*v1) * v2)),
                 _ => None
             }
             Self::Float(v1) => match other {
                 Self::Float(v2) => Some(V::Float(v1 * v2)),
                 Self::Int(v2) => Some(V::Float(v1 * (*v2 as f64))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal(*v1) * v2)),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Decimal(v2) => Some(V::Decimal(v1 * v2)),
+                Self::Int(v2) => Some(V::Decimal(v1 * Decimal::from(*v2))),
+                Self::Float(v2) => Some(V::Decimal(v1 * Self::float_to_decimal(*v2))),
                 _ => None
             }
             _ => None
         }
     }
     pub fn div(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().div(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.div(&other.deref()) }
         match self {
             Self::Int(v1) => match other {
                 Self::Int(v2) => Some(V::Float((*v1 as f64) / (*v2 as f64))),
                 Self::Float(v2) => Some(V::Float((*v1 as f64) / v2)),
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(Decimal::from(*v1) / v2)),
                 _ => None
             }
             Self::Float(v1) => match other {
                 Self::Float(v2) => Some(V::Float(v1 / v2)),
                 Self::Int(v2) => Some(V::Float(v1 / (*v2 as f64))),
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(Self::float_to_decimal(*v1) / v2)),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(v1 / v2)),
+                Self::Int(v2) if *v2 != 0 => Some(V::Decimal(v1 / Decimal::from(*v2))),
+                Self::Float(v2) => match Self::float_to_decimal(*v2) {
+                    divisor if !divisor.is_zero() => Some(V::Decimal(v1 / divisor)),
+                    _ => None
+                }
+                _ => None
+            }
+            _ => None
+        }
+    }
+    pub fn rem(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().rem(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.rem(&other.deref()) }
+        match self {
+            Self::Int(v1) => match other {
+                // Reject both the divide-by-zero and the `i64::MIN % -1` overflow.
+                Self::Int(v2) => v1.checked_rem(*v2).map(V::Int),
+                Self::Float(v2) => Some(V::Float((*v1 as f64) % v2)),
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(Decimal::from(*v1) % v2)),
+                _ => None
+            }
+            Self::Float(v1) => match other {
+                Self::Float(v2) => Some(V::Float(v1 % v2)),
+                Self::Int(v2) => Some(V::Float(v1 % (*v2 as f64))),
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(Self::float_to_decimal(*v1) % v2)),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Decimal(v2) if !v2.is_zero() => Some(V::Decimal(v1 % v2)),
+                Self::Int(v2) if *v2 != 0 => Some(V::Decimal(v1 % Decimal::from(*v2))),
+                Self::Float(v2) => match Self::float_to_decimal(*v2) {
+                    divisor if !divisor.is_zero() => Some(V::Decimal(v1 % divisor)),
+                    _ => None
+                }
+                _ => None
+            }
+            _ => None
+        }
+    }
+    /// Exponentiation. Integer and decimal bases raised to an integer exponent
+    /// stay exact (the `Int` path falls back to `Float` when the exponent is
+    /// negative or above `u32::MAX`, or when the result overflows `i64`); a
+    /// non-integer exponent inherently leaves the exact
+    /// domain, so those arms are computed as `f64` approximations.
+    pub fn pow(&self, other: &V) -> Option<V> {
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref().pow(other) }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return self.pow(&other.deref()) }
+        match self {
+            Self::Int(v1) => match other {
+                Self::Int(v2) if *v2 >= 0 => match u32::try_from(*v2).ok().and_then(|e| v1.checked_pow(e)) {
+                    Some(result) => Some(V::Int(result)),
+                    None => Some(V::Float((*v1 as f64).powf(*v2 as f64)))
+                }
+                Self::Int(v2) => Some(V::Float((*v1 as f64).powi(*v2 as i32))),
+                Self::Float(v2) => Some(V::Float((*v1 as f64).powf(*v2))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal((*v1 as f64).powf(v2.to_f64().unwrap_or(0.0))))),
+                _ => None
+            }
+            Self::Float(v1) => match other {
+                Self::Float(v2) => Some(V::Float(v1.powf(*v2))),
+                Self::Int(v2) => Some(V::Float(v1.powi(*v2 as i32))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal(v1.powf(v2.to_f64().unwrap_or(0.0))))),
+                _ => None
+            }
+            Self::Decimal(v1) => match other {
+                Self::Int(v2) => Self::decimal_powi(*v1, *v2).map(V::Decimal),
+                Self::Float(v2) => Some(V::Decimal(Self::float_to_decimal(v1.to_f64().unwrap_or(0.0).powf(*v2)))),
+                Self::Decimal(v2) => Some(V::Decimal(Self::float_to_decimal(v1.to_f64().unwrap_or(0.0).powf(v2.to_f64().unwrap_or(0.0))))),
                 _ => None
             }
             _ => None
         }
     }
+    fn float_to_decimal(v: f64) -> Decimal {
+        Decimal::from_f64(v).unwrap_or(Decimal::ZERO)
+    }
+    /// Raise a decimal to an integer power by exact repeated multiplication,
+    /// inverting for negative exponents. Returns `None` if an intermediate
+    /// product or the final division overflows the decimal range.
+    fn decimal_powi(base: Decimal, exp: i64) -> Option<Decimal> {
+        let mut acc = Decimal::ONE;
+        for _ in 0..exp.unsigned_abs() {
+            acc = acc.checked_mul(base)?;
+        }
+        if exp < 0 { Decimal::ONE.checked_div(acc) } else { Some(acc) }
+    }
+    /// Infer the principal type of this value. `Function` and `Closure` values
+    /// run Algorithm W over their body and report a (possibly universally
+    /// quantified) scheme such as `forall t0. fn(t0) -> t0`; every other value
+    /// reports its concrete [`Type`]. Falls back to [`V::typ`] if the body
+    /// cannot be reconciled.
+    pub fn infer(&self) -> Type {
+        crate::tc::infer_value(self).unwrap_or_else(|_| self.typ())
+    }
+    /// Serialize this value to a JSON string. Closures, functions and native
+    /// functions cannot be represented and surface as a serialization error.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+    /// Reconstruct a value from the JSON produced by [`V::to_json`].
+    pub fn from_json(source: &str) -> Result<V, serde_json::Error> {
+        serde_json::from_str(source)
+    }
 }
 impl std::fmt::Display for V {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -206,10 +461,15 @@ impl std::fmt::Debug for V {
             Self::Wirldcard => write!(f, "_"),
             Self::Int(v) => write!(f, "{v}"),
             Self::Float(v) => write!(f, "{v}"),
+            Self::Decimal(v) => write!(f, "{v}"),
             Self::Bool(v) => write!(f, "{v}"),
             Self::String(v) => write!(f, "{v}"),
             Self::Addr(v) => write!(f, "@{v}"),
-            Self::Closure(v) => write!(f, "#{v}"),
+            Self::Closure(v, _) => write!(f, "#{v}"),
+            Self::Reference(v) => write!(f, "&{v:?}"),
+            Self::Mutable(v) => write!(f, "&mut {:?}", v.read().unwrap()),
+            Self::List(values) => write!(f, "[{}]", values.iter().map(|v| format!("{v:?}")).collect::<Vec<String>>().join(", ")),
+            Self::Map(entries) => write!(f, "{{{}}}", entries.iter().map(|(k, v)| format!("{k}: {v:?}")).collect::<Vec<String>>().join(", ")),
             Self::NativFunction(_, v) => write!(f, "nativ-function:{:?}", v as *const NativFunction),
             Self::Function(_, body) => write!(f, "function:{:?}", body as *const Node),
             Self::Type(typ) => write!(f, "{typ}"),
@@ -220,15 +480,189 @@ impl PartialEq for V {
     fn eq(&self, other: &Self) -> bool {
         if let Self::Wirldcard = self { return true }
         if let Self::Wirldcard = other { return true }
+        if let Self::Reference(_) | Self::Mutable(_) = self { return self.deref() == *other }
+        if let Self::Reference(_) | Self::Mutable(_) = other { return *self == other.deref() }
         match (self, other) {
             (Self::Null, Self::Null) => true,
             (Self::Int(v1), Self::Int(v2)) => *v1 == *v2,
             (Self::Int(v1), Self::Float(v2)) => *v1 as f64 == *v2,
             (Self::Float(v1), Self::Int(v2)) => *v1 == *v2 as f64,
             (Self::Float(v1), Self::Float(v2)) => *v1 == *v2,
+            (Self::Decimal(v1), Self::Decimal(v2)) => v1 == v2,
+            (Self::Int(v1), Self::Decimal(v2)) => &Decimal::from(*v1) == v2,
+            (Self::Decimal(v1), Self::Int(v2)) => v1 == &Decimal::from(*v2),
+            // Compare in `f64` so non-finite floats (NaN/∞) never test equal to a
+            // finite decimal, which `float_to_decimal`'s zero fallback would do.
+            (Self::Float(v1), Self::Decimal(v2)) => v2.to_f64().map(|d| *v1 == d).unwrap_or(false),
+            (Self::Decimal(v1), Self::Float(v2)) => v1.to_f64().map(|d| d == *v2).unwrap_or(false),
             (Self::Bool(v1), Self::Bool(v2)) => v1 == v2,
             (Self::String(v1), Self::String(v2)) => v1 == v2,
+            (Self::List(v1), Self::List(v2)) => v1 == v2,
+            (Self::Map(v1), Self::Map(v2)) => {
+                if v1.len() != v2.len() { return false }
+                v1.iter().all(|(key, value)| match v2.iter().find(|(k, _)| k == key) {
+                    Some((_, other)) => value == other,
+                    None => false
+                })
+            }
             _ => false
         }
     }
-}
\ No newline at end of file
+}
+/// Serializable mirror of [`V`]. Every variant is tagged by name so scalars,
+/// addresses and collections survive a JSON/MessagePack round-trip. The
+/// function-like variants are deliberately absent: the parser's `Node` does not
+/// derive serde in this tree (and `Context` has no `Default`), so closures,
+/// functions and native functions have no representation and are rejected
+/// during conversion.
+#[derive(Serialize, Deserialize)]
+enum VRepr {
+    Null, Wirldcard, Int(i64), Float(f64), Decimal(Decimal), Bool(bool),
+    String(String), Addr(String), List(Vec<VRepr>), Map(Vec<(String, VRepr)>),
+    Reference(Box<VRepr>), Mutable(Box<VRepr>),
+    Type(Type)
+}
+impl TryFrom<&V> for VRepr {
+    type Error = String;
+    fn try_from(value: &V) -> Result<Self, Self::Error> {
+        Ok(match value {
+            V::Null => VRepr::Null,
+            V::Wirldcard => VRepr::Wirldcard,
+            V::Int(v) => VRepr::Int(*v),
+            V::Float(v) => VRepr::Float(*v),
+            V::Decimal(v) => VRepr::Decimal(*v),
+            V::Bool(v) => VRepr::Bool(*v),
+            V::String(v) => VRepr::String(v.clone()),
+            V::Addr(v) => VRepr::Addr(v.clone()),
+            V::List(values) => VRepr::List(values.iter().map(VRepr::try_from).collect::<Result<_, _>>()?),
+            V::Map(entries) => VRepr::Map(entries.iter().map(|(k, v)| Ok((k.clone(), VRepr::try_from(v)?))).collect::<Result<_, String>>()?),
+            V::Reference(inner) => VRepr::Reference(Box::new(VRepr::try_from(inner.as_ref())?)),
+            V::Mutable(inner) => VRepr::Mutable(Box::new(VRepr::try_from(&*inner.read().unwrap())?)),
+            V::Type(typ) => VRepr::Type(typ.clone()),
+            V::Closure(_, _) => return Err("cannot serialize a closure".to_string()),
+            V::Function(_, _) => return Err("cannot serialize a function".to_string()),
+            V::NativFunction(_, _) => return Err("cannot serialize a native function".to_string())
+        })
+    }
+}
+impl From<VRepr> for V {
+    fn from(repr: VRepr) -> Self {
+        match repr {
+            VRepr::Null => V::Null,
+            VRepr::Wirldcard => V::Wirldcard,
+            VRepr::Int(v) => V::Int(v),
+            VRepr::Float(v) => V::Float(v),
+            VRepr::Decimal(v) => V::Decimal(v),
+            VRepr::Bool(v) => V::Bool(v),
+            VRepr::String(v) => V::String(v),
+            VRepr::Addr(v) => V::Addr(v),
+            VRepr::List(values) => V::List(values.into_iter().map(V::from).collect()),
+            VRepr::Map(entries) => V::Map(entries.into_iter().map(|(k, v)| (k, V::from(v))).collect()),
+            VRepr::Reference(inner) => V::Reference(Arc::new(V::from(*inner))),
+            VRepr::Mutable(inner) => V::Mutable(Arc::new(RwLock::new(V::from(*inner)))),
+            VRepr::Type(typ) => V::Type(typ)
+        }
+    }
+}
+impl Serialize for V {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VRepr::try_from(self).map_err(S::Error::custom)?.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for V {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(VRepr::deserialize(deserializer)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_add_concatenates() {
+        let a = V::List(vec![V::Int(1), V::Int(2)]);
+        let b = V::List(vec![V::Int(3)]);
+        assert_eq!(a.add(&b), Some(V::List(vec![V::Int(1), V::Int(2), V::Int(3)])));
+    }
+
+    #[test]
+    fn list_eq_is_positional() {
+        assert_eq!(V::List(vec![V::Int(1), V::Int(2)]), V::List(vec![V::Int(1), V::Int(2)]));
+        assert_ne!(V::List(vec![V::Int(1), V::Int(2)]), V::List(vec![V::Int(2), V::Int(1)]));
+    }
+
+    #[test]
+    fn map_eq_ignores_order() {
+        let a = V::Map(vec![("x".to_string(), V::Int(1)), ("y".to_string(), V::Int(2))]);
+        let b = V::Map(vec![("y".to_string(), V::Int(2)), ("x".to_string(), V::Int(1))]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn map_add_merges_and_overwrites() {
+        let a = V::Map(vec![("x".to_string(), V::Int(1)), ("y".to_string(), V::Int(2))]);
+        let b = V::Map(vec![("y".to_string(), V::Int(9)), ("z".to_string(), V::Int(3))]);
+        let merged = a.add(&b).unwrap();
+        let expected = V::Map(vec![
+            ("x".to_string(), V::Int(1)),
+            ("y".to_string(), V::Int(9)),
+            ("z".to_string(), V::Int(3)),
+        ]);
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn numeric_eq_across_types() {
+        assert_eq!(V::Int(2), V::Float(2.0));
+        assert_eq!(V::Float(2.0), V::Decimal(Decimal::from(2)));
+        assert_eq!(V::Int(2), V::Decimal(Decimal::from(2)));
+        assert_ne!(V::Int(2), V::Float(2.5));
+    }
+
+    #[test]
+    fn rem_coerces_to_widest_type() {
+        assert_eq!(V::Int(7).rem(&V::Int(3)), Some(V::Int(1)));
+        assert_eq!(V::Int(7).rem(&V::Decimal(Decimal::from(3))), Some(V::Decimal(Decimal::from(1))));
+    }
+
+    #[test]
+    fn pow_int_overflow_falls_back_to_float() {
+        assert_eq!(V::Int(2).pow(&V::Int(10)), Some(V::Int(1024)));
+        match V::Int(2).pow(&V::Int(64)) {
+            Some(V::Float(v)) => assert!((v - 2f64.powi(64)).abs() < 1.0),
+            other => panic!("expected float fallback, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn rem_and_div_by_zero_return_none() {
+        assert_eq!(V::Int(5).rem(&V::Int(0)), None);
+        assert_eq!(V::Int(i64::MIN).rem(&V::Int(-1)), None);
+        assert_eq!(V::Int(5).div(&V::Decimal(Decimal::ZERO)), None);
+        assert_eq!(V::Decimal(Decimal::from(5)).rem(&V::Int(0)), None);
+    }
+
+    #[test]
+    fn non_finite_float_never_equals_decimal() {
+        assert_ne!(V::Float(f64::NAN), V::Decimal(Decimal::ZERO));
+        assert_ne!(V::Float(f64::INFINITY), V::Decimal(Decimal::ZERO));
+        assert_ne!(V::Decimal(Decimal::ZERO), V::Float(f64::NAN));
+    }
+
+    #[test]
+    fn pow_decimal_int_is_exact() {
+        let tenth = Decimal::new(1, 1); // 0.1
+        assert_eq!(V::Decimal(tenth).pow(&V::Int(2)), Some(V::Decimal(Decimal::new(1, 2)))); // 0.01
+    }
+
+    #[test]
+    fn index_and_len() {
+        let list = V::List(vec![V::Int(10), V::Int(20)]);
+        assert_eq!(list.index(&V::Int(1)), Some(V::Int(20)));
+        assert_eq!(list.len(), Some(2));
+        let map = V::Map(vec![("k".to_string(), V::Bool(true))]);
+        assert_eq!(map.index(&V::String("k".to_string())), Some(V::Bool(true)));
+        assert_eq!(map.len(), Some(1));
+    }
+}